@@ -0,0 +1,93 @@
+extern crate proc_macro;
+
+use proc_macro::TokenStream;
+use quote::quote;
+use syn::{parse_macro_input, Data, DeriveInput, Fields, Lit, Meta, NestedMeta};
+
+/// Walks a struct's named fields in declaration order and implements `glrs::vertex::Vertex` for
+/// it, replacing a hand-maintained `implement_vertex!` call with one that can't silently omit a
+/// field. `#[glrs(normalized)]` marks an integer field as normalized instead of bound as an
+/// `ivec`/`uvec`; `#[glrs(divisor = N)]` sets the instancing divisor for the whole binding.
+#[proc_macro_derive(Vertex, attributes(glrs))]
+pub fn derive_vertex(input: TokenStream) -> TokenStream {
+	let input = parse_macro_input!(input as DeriveInput);
+	let struct_name = &input.ident;
+
+	let fields = match input.data {
+		Data::Struct(data) => match data.fields {
+			Fields::Named(fields) => fields.named,
+			_ => panic!("#[derive(Vertex)] requires a struct with named fields"),
+		},
+		_ => panic!("#[derive(Vertex)] only supports structs"),
+	};
+
+	let mut divisor = None;
+	let formats = fields.iter().map(|field| {
+		let field_name = field.ident.as_ref().unwrap();
+		let field_ty = &field.ty;
+		let normalized = has_glrs_flag(&field.attrs, "normalized");
+
+		if let Some(field_divisor) = glrs_divisor(&field.attrs) {
+			divisor = Some(field_divisor);
+		}
+
+		let attrib_ty = if normalized {
+			quote! { glrs::vertex::Normalized<#field_ty> }
+		} else {
+			quote! { #field_ty }
+		};
+
+		quote! {
+			glrs::vertex::VertexAttributeFormat {
+				offset: glrs::memoffset::offset_of!(#struct_name, #field_name) as _,
+				size: <#attrib_ty as glrs::vertex::VertexAttribute>::size(),
+				typ: <#attrib_ty as glrs::vertex::VertexAttribute>::typ(),
+				class: <#attrib_ty as glrs::vertex::VertexAttribute>::class(),
+			}
+		}
+	});
+	let formats = formats.collect::<Vec<_>>();
+	let divisor = divisor.unwrap_or(0);
+
+	let expanded = quote! {
+		impl glrs::vertex::Vertex for #struct_name {
+			fn format() -> Vec<glrs::vertex::VertexAttributeFormat> {
+				vec![ #( #formats ),* ]
+			}
+
+			fn divisor() -> glrs::gl::types::GLuint {
+				#divisor
+			}
+		}
+	};
+	expanded.into()
+}
+
+fn has_glrs_flag(attrs: &[syn::Attribute], flag: &str) -> bool {
+	glrs_meta(attrs).iter().any(|meta| meta.path().is_ident(flag))
+}
+
+fn glrs_divisor(attrs: &[syn::Attribute]) -> Option<u32> {
+	glrs_meta(attrs).into_iter().find_map(|meta| match meta {
+		Meta::NameValue(nv) if nv.path.is_ident("divisor") => match nv.lit {
+			Lit::Int(int) => int.base10_parse().ok(),
+			_ => None,
+		},
+		_ => None,
+	})
+}
+
+fn glrs_meta(attrs: &[syn::Attribute]) -> Vec<Meta> {
+	attrs
+		.iter()
+		.filter(|attr| attr.path.is_ident("glrs"))
+		.filter_map(|attr| attr.parse_meta().ok())
+		.flat_map(|meta| match meta {
+			Meta::List(list) => list.nested.into_iter().filter_map(|nested| match nested {
+				NestedMeta::Meta(meta) => Some(meta),
+				_ => None,
+			}).collect(),
+			_ => vec![],
+		})
+		.collect()
+}
@@ -1,6 +1,6 @@
-use crate::vertex::VertexArray;
-use gl::types::GLuint;
-use std::ffi::c_void;
+use crate::{buffer::DynamicBuffer, vertex::VertexArray};
+use gl::types::{GLintptr, GLsizei, GLuint};
+use std::{ffi::c_void, rc::Rc};
 
 pub trait CommandBufferAbstract<C> {
 	fn vao(&self) -> &VertexArray;
@@ -64,3 +64,69 @@ pub struct DrawElementsIndirectCommand {
 	pub base_vertex: u32,
 	pub base_instance: u32,
 }
+
+pub struct DrawCount {
+	buffer: Rc<DynamicBuffer<u32>>,
+	offset: GLintptr,
+	max_draw_count: GLsizei,
+}
+impl DrawCount {
+	pub fn new(buffer: Rc<DynamicBuffer<u32>>, offset: GLintptr, max_draw_count: GLsizei) -> Self {
+		Self { buffer, offset, max_draw_count }
+	}
+
+	pub fn handle(&self) -> GLuint {
+		self.buffer.handle()
+	}
+
+	pub fn offset(&self) -> GLintptr {
+		self.offset
+	}
+
+	pub fn max_draw_count(&self) -> GLsizei {
+		self.max_draw_count
+	}
+}
+
+pub struct IndirectCommandBuffer<'a, C> {
+	vao: &'a VertexArray,
+	buffer: Rc<DynamicBuffer<C>>,
+	offset: GLintptr,
+	draw_count: Option<DrawCount>,
+}
+impl<'a, C> IndirectCommandBuffer<'a, C> {
+	pub fn new(vao: &'a VertexArray, buffer: Rc<DynamicBuffer<C>>) -> Self {
+		Self { vao, buffer, offset: 0, draw_count: None }
+	}
+
+	pub fn with_offset(mut self, offset: GLintptr) -> Self {
+		self.offset = offset;
+		self
+	}
+
+	pub fn with_draw_count(mut self, draw_count: DrawCount) -> Self {
+		self.draw_count = Some(draw_count);
+		self
+	}
+
+	pub fn draw_count(&self) -> Option<&DrawCount> {
+		self.draw_count.as_ref()
+	}
+}
+impl<'a, C> CommandBufferAbstract<C> for IndirectCommandBuffer<'a, C> {
+	fn vao(&self) -> &VertexArray {
+		self.vao
+	}
+
+	fn handle(&self) -> GLuint {
+		self.buffer.handle()
+	}
+
+	fn len(&self) -> usize {
+		self.buffer.len()
+	}
+
+	fn indirect(&self) -> *const c_void {
+		self.offset as *const c_void
+	}
+}
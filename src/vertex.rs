@@ -8,8 +8,9 @@ use nalgebra::{allocator::Allocator as NAllocator, DefaultAllocator, Dim, DimNam
 use simba::simd::SimdValue;
 use std::{
 	cell::{Cell, RefCell},
+	collections::HashMap,
 	mem::size_of,
-	rc::Rc,
+	rc::{Rc, Weak},
 };
 
 #[macro_export]
@@ -18,15 +19,19 @@ macro_rules! implement_vertex {
 		impl $crate::vertex::Vertex for $struct {
 			fn format() -> Vec<$crate::vertex::VertexAttributeFormat> {
 				fn glformat<T: $crate::vertex::VertexAttribute>(_: Option<&T>)
-					-> ($crate::gl::types::GLint, $crate::gl::types::GLenum)
+					-> ($crate::gl::types::GLint, $crate::gl::types::GLenum, $crate::vertex::AttribClass)
 				{
-					(<T as $crate::vertex::VertexAttribute>::size(), <T as $crate::vertex::VertexAttribute>::typ())
+					(
+						<T as $crate::vertex::VertexAttribute>::size(),
+						<T as $crate::vertex::VertexAttribute>::typ(),
+						<T as $crate::vertex::VertexAttribute>::class(),
+					)
 				}
 
 				vec![ $( {
 					let offset = $crate::memoffset::offset_of!($struct, $field) as _;
-					let (size, typ) = glformat(None::<&$struct>.map(|x| &x.$field));
-					$crate::vertex::VertexAttributeFormat { offset, size, typ }
+					let (size, typ, class) = glformat(None::<&$struct>.map(|x| &x.$field));
+					$crate::vertex::VertexAttributeFormat { offset, size, typ, class }
 				} ),+ ]
 			}
 		}
@@ -34,7 +39,7 @@ macro_rules! implement_vertex {
 }
 
 macro_rules! implement_attribute {
-	($ty:ty, $size:expr, $typ:expr) => {
+	($ty:ty, $size:expr, $typ:expr, $class:expr) => {
 		impl VertexAttribute for $ty {
 			fn size() -> GLint {
 				$size
@@ -43,21 +48,45 @@ macro_rules! implement_attribute {
 			fn typ() -> GLenum {
 				$typ
 			}
+
+			fn class() -> AttribClass {
+				$class
+			}
 		}
 	};
 }
 
 pub trait Vertex {
 	fn format() -> Vec<VertexAttributeFormat>;
+
+	fn divisor() -> GLuint {
+		0
+	}
+}
+
+/// See `glrs_derive::derive_vertex` for what this derives and which field attributes it supports.
+pub use glrs_derive::Vertex;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum AttribClass {
+	Float { normalized: bool },
+	Integer,
+	Long,
 }
 
 pub trait VertexAttribute {
 	fn size() -> GLint;
 	fn typ() -> GLenum;
+	fn class() -> AttribClass;
 }
-implement_attribute!(u8, 1, gl::UNSIGNED_BYTE);
-implement_attribute!(u32, 1, gl::UNSIGNED_INT);
-implement_attribute!(f32, 1, gl::FLOAT);
+implement_attribute!(u8, 1, gl::UNSIGNED_BYTE, AttribClass::Integer);
+implement_attribute!(i8, 1, gl::BYTE, AttribClass::Integer);
+implement_attribute!(u16, 1, gl::UNSIGNED_SHORT, AttribClass::Integer);
+implement_attribute!(i16, 1, gl::SHORT, AttribClass::Integer);
+implement_attribute!(u32, 1, gl::UNSIGNED_INT, AttribClass::Integer);
+implement_attribute!(i32, 1, gl::INT, AttribClass::Integer);
+implement_attribute!(f32, 1, gl::FLOAT, AttribClass::Float { normalized: false });
+implement_attribute!(f64, 1, gl::DOUBLE, AttribClass::Long);
 impl<N: Scalar + VertexAttribute, D: Dim + DimName> VertexAttribute for VectorN<N, D>
 where
 	DefaultAllocator: NAllocator<N, D>,
@@ -69,6 +98,10 @@ where
 	fn typ() -> GLenum {
 		N::typ()
 	}
+
+	fn class() -> AttribClass {
+		N::class()
+	}
 }
 impl<N: Scalar + SimdValue + VertexAttribute> VertexAttribute for Quaternion<N> {
 	fn size() -> GLint {
@@ -78,6 +111,10 @@ impl<N: Scalar + SimdValue + VertexAttribute> VertexAttribute for Quaternion<N>
 	fn typ() -> GLenum {
 		N::typ()
 	}
+
+	fn class() -> AttribClass {
+		N::class()
+	}
 }
 impl<T: VertexAttribute> VertexAttribute for Unit<T> {
 	fn size() -> GLint {
@@ -87,13 +124,33 @@ impl<T: VertexAttribute> VertexAttribute for Unit<T> {
 	fn typ() -> GLenum {
 		T::typ()
 	}
+
+	fn class() -> AttribClass {
+		T::class()
+	}
+}
+
+pub struct Normalized<T>(pub T);
+impl<T: VertexAttribute> VertexAttribute for Normalized<T> {
+	fn size() -> GLint {
+		T::size()
+	}
+
+	fn typ() -> GLenum {
+		T::typ()
+	}
+
+	fn class() -> AttribClass {
+		AttribClass::Float { normalized: true }
+	}
 }
 
-#[derive(Debug, PartialEq, Eq)]
+#[derive(Debug, Clone, PartialEq, Eq, Hash)]
 pub struct VertexAttributeFormat {
 	pub offset: GLuint,
 	pub size: GLint,
 	pub typ: GLenum,
+	pub class: AttribClass,
 }
 
 pub struct VertexArray {
@@ -118,15 +175,26 @@ impl VertexArray {
 		}
 	}
 
-	pub fn enable_vertices<V: Vertex>(&self, divisor: GLuint) {
-		let format = V::format();
+	pub fn enable_vertices<V: Vertex>(&self, divisor: impl Into<Option<GLuint>>) {
+		let divisor = divisor.into().unwrap_or_else(V::divisor);
+		self.enable_format(V::format(), divisor);
+	}
+
+	fn enable_format(&self, format: Vec<VertexAttributeFormat>, divisor: GLuint) {
 		let gl = &self.ctx.gl;
-		for &VertexAttributeFormat { offset, size, typ } in &format {
+		for &VertexAttributeFormat { offset, size, typ, class } in &format {
 			let next_attrib = self.next_attrib.get();
 			let formats_len = self.formats.borrow().len() as _;
 			unsafe {
 				gl.EnableVertexArrayAttrib(self.handle, next_attrib);
-				gl.VertexArrayAttribFormat(self.handle, next_attrib, size, typ, gl::FALSE, offset);
+				match class {
+					AttribClass::Float { normalized } => {
+						let normalized = if normalized { gl::TRUE } else { gl::FALSE };
+						gl.VertexArrayAttribFormat(self.handle, next_attrib, size, typ, normalized, offset);
+					}
+					AttribClass::Integer => gl.VertexArrayAttribIFormat(self.handle, next_attrib, size, typ, offset),
+					AttribClass::Long => gl.VertexArrayAttribLFormat(self.handle, next_attrib, size, typ, offset),
+				}
 				gl.VertexArrayAttribBinding(self.handle, next_attrib, formats_len);
 				gl.VertexArrayBindingDivisor(self.handle, formats_len, divisor);
 			}
@@ -137,6 +205,12 @@ impl VertexArray {
 		self.vertex_buffers.borrow_mut().push(None);
 	}
 
+	fn bind_vertex_buffer_raw(&self, binding: usize, buffer: &dyn AllocatorAbstract, stride: GLint) {
+		let vertex_buffer = buffer.buffer();
+		unsafe { self.ctx.gl.VertexArrayVertexBuffer(self.handle, binding as _, vertex_buffer.handle(), 0, stride) };
+		self.vertex_buffers.borrow_mut()[binding] = Some(vertex_buffer.clone());
+	}
+
 	pub fn element_buffer(&self, element_buffer: &Allocator<u16>) {
 		let element_buffer = element_buffer.buffer();
 		unsafe { self.ctx.gl.VertexArrayElementBuffer(self.handle, element_buffer.handle()) };
@@ -165,3 +239,121 @@ impl Drop for VertexArray {
 		unsafe { self.ctx.gl.DeleteVertexArrays(1, &self.handle) };
 	}
 }
+
+#[derive(Default)]
+pub struct VertexArrayDesc<'a> {
+	bindings: Vec<BindingDesc<'a>>,
+	element_buffer: Option<&'a Allocator<u16>>,
+}
+struct BindingDesc<'a> {
+	format: Vec<VertexAttributeFormat>,
+	buffer: &'a dyn AllocatorAbstract,
+	stride: GLint,
+	divisor: GLuint,
+}
+impl<'a> VertexArrayDesc<'a> {
+	pub fn new() -> Self {
+		Self { bindings: vec![], element_buffer: None }
+	}
+
+	pub fn binding<V: Vertex>(&mut self, vertex_buffer: &'a Allocator<V>, divisor: impl Into<Option<GLuint>>) -> &mut Self {
+		let divisor = divisor.into().unwrap_or_else(V::divisor);
+		self.bindings.push(BindingDesc { format: V::format(), buffer: vertex_buffer, stride: size_of::<V>() as _, divisor });
+		self
+	}
+
+	pub fn element_buffer(&mut self, element_buffer: &'a Allocator<u16>) -> &mut Self {
+		self.element_buffer = Some(element_buffer);
+		self
+	}
+
+	pub fn build(self, ctx: &Rc<Ctx>) -> Result<VertexArray, VertexArrayDescError> {
+		for (binding, desc) in self.bindings.iter().enumerate() {
+			let mut sorted: Vec<_> = desc.format.iter().collect();
+			sorted.sort_by_key(|format| format.offset);
+			for pair in sorted.windows(2) {
+				if let [a, b] = pair {
+					if a.offset + attrib_byte_len(a)? > b.offset {
+						return Err(VertexArrayDescError::OverlappingAttributes(binding));
+					}
+				}
+			}
+		}
+
+		let vao = VertexArray::new(ctx);
+		for (binding, desc) in self.bindings.into_iter().enumerate() {
+			vao.enable_format(desc.format, desc.divisor);
+			vao.bind_vertex_buffer_raw(binding, desc.buffer, desc.stride);
+		}
+		if let Some(element_buffer) = self.element_buffer {
+			vao.element_buffer(element_buffer);
+		}
+		Ok(vao)
+	}
+}
+
+fn attrib_byte_len(format: &VertexAttributeFormat) -> Result<GLuint, VertexArrayDescError> {
+	let component_size = match format.typ {
+		gl::BYTE | gl::UNSIGNED_BYTE => 1,
+		gl::SHORT | gl::UNSIGNED_SHORT => 2,
+		gl::INT | gl::UNSIGNED_INT | gl::FLOAT => 4,
+		gl::DOUBLE => 8,
+		typ => return Err(VertexArrayDescError::UnknownAttributeType(typ)),
+	};
+	Ok(format.size as GLuint * component_size)
+}
+
+#[derive(Debug)]
+pub enum VertexArrayDescError {
+	OverlappingAttributes(usize),
+	UnknownAttributeType(GLenum),
+}
+impl std::fmt::Display for VertexArrayDescError {
+	fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+		match self {
+			Self::OverlappingAttributes(binding) => write!(f, "binding {} has overlapping vertex attributes", binding),
+			Self::UnknownAttributeType(typ) => write!(f, "unknown vertex attribute component type {}", typ),
+		}
+	}
+}
+impl std::error::Error for VertexArrayDescError {}
+
+#[derive(PartialEq, Eq, Hash)]
+struct VaoCacheKey {
+	bindings: Vec<(GLuint, GLint, GLuint, Vec<VertexAttributeFormat>)>,
+	element_buffer: Option<GLuint>,
+}
+impl VaoCacheKey {
+	fn new(desc: &VertexArrayDesc) -> Self {
+		let bindings = desc
+			.bindings
+			.iter()
+			.map(|binding| (binding.buffer.buffer().handle(), binding.stride, binding.divisor, binding.format.clone()))
+			.collect();
+		let element_buffer = desc.element_buffer.map(|element_buffer| element_buffer.buffer().handle());
+		Self { bindings, element_buffer }
+	}
+}
+
+#[derive(Default)]
+pub struct VaoCache {
+	entries: RefCell<HashMap<VaoCacheKey, Weak<VertexArray>>>,
+}
+impl VaoCache {
+	pub fn new() -> Self {
+		Self { entries: RefCell::new(HashMap::new()) }
+	}
+
+	pub fn get_or_insert(&self, ctx: &Rc<Ctx>, desc: VertexArrayDesc) -> Result<Rc<VertexArray>, VertexArrayDescError> {
+		let key = VaoCacheKey::new(&desc);
+		if let Some(vao) = self.entries.borrow().get(&key).and_then(Weak::upgrade) {
+			return Ok(vao);
+		}
+
+		let vao = Rc::new(desc.build(ctx)?);
+		let mut entries = self.entries.borrow_mut();
+		entries.retain(|_, cached| cached.strong_count() > 0);
+		entries.insert(key, Rc::downgrade(&vao));
+		Ok(vao)
+	}
+}